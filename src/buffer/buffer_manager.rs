@@ -1,26 +1,155 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::vec::Vec;
 
 use crate::common::{BuzzDBError, PageID, Result};
+use crate::log_mod::RecoverablePageStore;
+
+/// Owns the on-disk file backing a `BufferManager` and does the raw paging I/O,
+/// seeking to `page_id * page_size` for every page read and write.
+pub struct DiskManager {
+    file: File,
+    page_size: usize,
+}
+
+impl DiskManager {
+    pub fn new(file_path: &Path, page_size: usize) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(file_path)
+            .map_err(BuzzDBError::IOError)?;
+
+        Ok(Self { file, page_size })
+    }
+
+    /// A disk manager backed by an unlinked temp file, for buffer managers that don't
+    /// need real persistence (e.g. standalone tests) but should still round-trip pages.
+    pub fn temporary(page_size: usize) -> Result<Self> {
+        use std::env::temp_dir;
+        use uuid::Uuid;
+
+        let temp_path = temp_dir().join(format!("buzzdb-buffer-{}.tmp", Uuid::new_v4()));
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&temp_path)
+            .map_err(BuzzDBError::IOError)?;
+
+        // Delete the file immediately - it'll stay open but disappear from filesystem
+        std::fs::remove_file(&temp_path).map_err(BuzzDBError::IOError)?;
+
+        Ok(Self { file, page_size })
+    }
+
+    /// Reads one page-sized block. A page past the current end of file hasn't been
+    /// written yet and reads back as all-zero.
+    pub fn read_page(&mut self, page_id: PageID) -> Result<Vec<u8>> {
+        let offset = page_id.0 * self.page_size as u64;
+        let mut buffer = vec![0u8; self.page_size];
+
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .map_err(BuzzDBError::IOError)?;
+
+        // read_exact instead of a single read: a short read (e.g. the file doesn't extend this
+        // far yet) must not silently hand back a half-filled buffer. A page that doesn't exist
+        // yet is an UnexpectedEof, which we treat as the all-zero page; anything else is a real
+        // I/O error.
+        match self.file.read_exact(&mut buffer) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                buffer = vec![0u8; self.page_size];
+            }
+            Err(e) => return Err(BuzzDBError::IOError(e)),
+        }
+
+        Ok(buffer)
+    }
+
+    pub fn write_page(&mut self, page_id: PageID, data: &[u8]) -> Result<()> {
+        if data.len() > self.page_size {
+            return Err(BuzzDBError::PageSizeExceeded(data.len(), self.page_size));
+        }
+
+        let offset = page_id.0 * self.page_size as u64;
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .map_err(BuzzDBError::IOError)?;
+        self.file.write_all(data).map_err(BuzzDBError::IOError)?;
+
+        Ok(())
+    }
+}
+
+/// Default K for the LRU-K replacer used by `BufferManager` when no
+/// override is given - 2 is the value bustubx and most textbook
+/// implementations settle on.
+pub const DEFAULT_LRU_K: usize = 2;
 
 pub struct BufferFrame {
     page_id: PageID,
     data: Vec<u8>,
     is_dirty: bool,
-    pin_count: u32, // Reference count - can't evict while > 0
+    pin_count: u32,                    // Reference count - can't evict while > 0
+    access_history: VecDeque<Instant>, // Most recent accesses, oldest first, bounded to K entries
+    page_lsn: u64, // LSN of the last log record applied to this page - the WAL invariant hinges on this
 }
 
 impl BufferFrame {
     pub fn new(page_id: PageID, page_size: usize) -> Self {
         Self {
-            page_id: page_id,
+            page_id,
             data: vec![0; page_size],
             is_dirty: false,
             pin_count: 0,
+            access_history: VecDeque::new(),
+            page_lsn: 0,
         }
     }
 
+    /// Builds a frame from bytes already read off disk, instead of a freshly zeroed page.
+    fn from_disk(page_id: PageID, data: Vec<u8>) -> Self {
+        Self {
+            page_id,
+            data,
+            is_dirty: false,
+            pin_count: 0,
+            access_history: VecDeque::new(),
+            page_lsn: 0,
+        }
+    }
+
+    /// Records an access for the LRU-K replacer, keeping only the `k` most recent timestamps.
+    fn record_access(&mut self, k: usize) {
+        self.access_history.push_back(Instant::now());
+        while self.access_history.len() > k {
+            self.access_history.pop_front();
+        }
+    }
+
+    /// Backward K-distance: time since the K-th most recent access, measured from `now`.
+    /// Returns `None` if fewer than `k` accesses have been recorded yet (infinite distance).
+    fn backward_k_distance(&self, k: usize, now: Instant) -> Option<Duration> {
+        if self.access_history.len() < k {
+            return None;
+        }
+        // With at most k entries retained, the front is the k-th most recent access.
+        Some(now.duration_since(self.access_history[0]))
+    }
+
+    /// Oldest recorded access, used to break ties among frames with infinite K-distance.
+    fn earliest_access(&self) -> Option<Instant> {
+        self.access_history.front().copied()
+    }
+
     pub fn get_data(&self) -> &[u8] {
         &self.data
     }
@@ -58,23 +187,56 @@ impl BufferFrame {
     pub fn get_page_id(&self) -> PageID {
         self.page_id
     }
+
+    pub fn page_lsn(&self) -> u64 {
+        self.page_lsn
+    }
+
+    pub fn set_page_lsn(&mut self, lsn: u64) {
+        self.page_lsn = lsn;
+    }
 }
 
 pub struct BufferManager {
     frames: HashMap<PageID, Arc<Mutex<BufferFrame>>>,
     page_size: usize,
     capacity: usize,
+    replacer_k: usize, // K for the LRU-K eviction policy
+    disk_manager: DiskManager,
 }
 
 impl BufferManager {
     pub fn new(page_size: usize, capacity: usize) -> Self {
+        Self::with_replacer_k(page_size, capacity, DEFAULT_LRU_K)
+    }
+
+    /// Like `new`, but lets callers tune K for the LRU-K replacer instead of taking the default.
+    /// Pages aren't backed by a real file - use `with_disk_file` when persistence matters.
+    pub fn with_replacer_k(page_size: usize, capacity: usize, replacer_k: usize) -> Self {
+        let disk_manager = DiskManager::temporary(page_size)
+            .expect("failed to create temporary backing file for buffer manager");
+
         Self {
             frames: HashMap::with_capacity(capacity),
             page_size,
             capacity,
+            replacer_k: replacer_k.max(1),
+            disk_manager,
         }
     }
 
+    /// Backs the buffer manager with a real file, so pages fixed here are actually
+    /// persisted by `flush_page`/`flush_all_pages` and reloaded on a later miss.
+    pub fn with_disk_file(file_path: &Path, page_size: usize, capacity: usize) -> Result<Self> {
+        Ok(Self {
+            frames: HashMap::with_capacity(capacity),
+            page_size,
+            capacity,
+            replacer_k: DEFAULT_LRU_K,
+            disk_manager: DiskManager::new(file_path, page_size)?,
+        })
+    }
+
     pub fn fix_page(
         &mut self,
         page_id: PageID,
@@ -84,6 +246,7 @@ impl BufferManager {
         if let Some(frame) = self.frames.get(&page_id) {
             let mut frame = frame.lock().unwrap();
             frame.pin();
+            frame.record_access(self.replacer_k);
             return Ok(Arc::clone(&self.frames[&page_id]));
         }
 
@@ -96,12 +259,14 @@ impl BufferManager {
             self.evict_page()?;
         }
 
-        let frame = BufferFrame::new(page_id, self.page_size);
+        let data = self.disk_manager.read_page(page_id)?;
+        let frame = BufferFrame::from_disk(page_id, data);
         let frame = Arc::new(Mutex::new(frame));
 
         {
             let mut frame_guard = frame.lock().unwrap();
             frame_guard.pin();
+            frame_guard.record_access(self.replacer_k);
 
             // Mark dirty upfront for exclusive access to avoid WAL issues
             if is_exclusive {
@@ -114,6 +279,25 @@ impl BufferManager {
         Ok(frame)
     }
 
+    /// Snapshot of the Dirty Page Table as seen from this buffer manager's side: every
+    /// currently-dirty frame mapped to its recLSN (`page_lsn()`, or 0 if no WAL writer has
+    /// ever stamped it). Folded by `TransactionManager::checkpoint` into the same Dirty
+    /// Page Table `HeapSegment::dirty_page_table` contributes to, so a checkpoint covers
+    /// both persistence pathways.
+    pub fn dirty_page_table(&self) -> HashMap<PageID, u64> {
+        self.frames
+            .iter()
+            .filter_map(|(&page_id, frame)| {
+                let frame = frame.lock().unwrap();
+                if frame.is_dirty() {
+                    Some((page_id, frame.page_lsn()))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     pub fn unfix_page(&mut self, frame: Arc<Mutex<BufferFrame>>, is_dirty: bool) -> Result<()> {
         let mut frame = frame.lock().unwrap();
 
@@ -128,10 +312,10 @@ impl BufferManager {
 
     pub fn flush_page(&mut self, page_id: PageID) -> Result<()> {
         if let Some(frame) = self.frames.get(&page_id) {
-            let frame = frame.lock().unwrap();
+            let mut frame = frame.lock().unwrap();
             if frame.is_dirty() {
-                // TODO: Write page to disk
-                // For now we're just pretending it worked
+                self.disk_manager.write_page(page_id, frame.get_data())?;
+                frame.set_dirty(false);
             }
         }
 
@@ -182,24 +366,46 @@ impl BufferManager {
         false
     }
 
+    // LRU-K: evict the unpinned frame with the largest backward K-distance (time since its
+    // K-th most recent access). Frames with fewer than K recorded accesses have infinite
+    // distance and are preferred for eviction; ties among those go to the one whose single
+    // oldest access is furthest in the past.
     fn evict_page(&mut self) -> Result<()> {
-        // Simple eviction: grab the first unpinned page we find
-        // TODO: Implement proper LRU replacement policy
-        let page_id_to_evict = {
-            let mut page_id_to_evict = None;
+        let now = Instant::now();
 
-            for (page_id, frame) in &self.frames {
-                let frame = frame.lock().unwrap();
-                if frame.pin_count() == 0 {
-                    page_id_to_evict = Some(*page_id);
-                    break;
-                }
+        let mut victim: Option<PageID> = None;
+        let mut victim_distance: Option<Duration> = None; // None == infinite distance
+        let mut victim_earliest: Option<Instant> = None;
+
+        for (page_id, frame) in &self.frames {
+            let frame = frame.lock().unwrap();
+            if frame.pin_count() != 0 {
+                continue;
             }
 
-            page_id_to_evict
-        };
+            let distance = frame.backward_k_distance(self.replacer_k, now);
+            let earliest = frame.earliest_access();
+
+            let replace = match (victim, distance) {
+                (None, _) => true,
+                (Some(_), None) => match victim_distance {
+                    None => earliest < victim_earliest,
+                    Some(_) => true,
+                },
+                (Some(_), Some(d)) => match victim_distance {
+                    None => false,
+                    Some(vd) => d > vd,
+                },
+            };
+
+            if replace {
+                victim = Some(*page_id);
+                victim_distance = distance;
+                victim_earliest = earliest;
+            }
+        }
 
-        if let Some(page_id) = page_id_to_evict {
+        if let Some(page_id) = victim {
             self.flush_page(page_id)?;
 
             self.frames.remove(&page_id);
@@ -227,3 +433,35 @@ impl BufferManager {
         overall_page_id.0 & 0x0000FFFFFFFFFFFF
     }
 }
+
+impl RecoverablePageStore for BufferManager {
+    /// `BufferManager` is the catch-all store - it doesn't tag the page_ids it's given with
+    /// a segment, so it can't definitively rule any of them out. `PageStores` registers it
+    /// last, after every `HeapSegment`, so this only gets to claim a page_id none of them did.
+    fn owns(&self, _page_id: PageID) -> bool {
+        true
+    }
+
+    fn page_lsn(&mut self, page_id: PageID) -> Result<u64> {
+        let frame = self.fix_page(page_id, false)?;
+        let lsn = frame.lock().unwrap().page_lsn();
+        self.unfix_page(frame, false)?;
+        Ok(lsn)
+    }
+
+    fn apply_patch(&mut self, page_id: PageID, offset: u64, patch: &[u8]) -> Result<()> {
+        let frame = self.fix_page(page_id, true)?;
+        {
+            let mut frame_guard = frame.lock().unwrap();
+            let data = frame_guard.get_data_mut();
+            data[offset as usize..offset as usize + patch.len()].copy_from_slice(patch);
+        }
+        self.unfix_page(frame, true)
+    }
+
+    fn set_page_lsn(&mut self, page_id: PageID, lsn: u64) -> Result<()> {
+        let frame = self.fix_page(page_id, false)?;
+        frame.lock().unwrap().set_page_lsn(lsn);
+        self.unfix_page(frame, true)
+    }
+}
@@ -1,8 +1,24 @@
-#[derive(Debug, Clone)]
+use std::sync::Arc;
+
+use crate::common::durability::Durability;
+use crate::common::vault::{Compressor, Vault};
+
+#[derive(Clone)]
 pub struct DatabaseConfig {
     pub page_size: usize,
     pub buffer_pool_size: usize,
     pub max_wal_size_mb: usize,
+    /// At-rest protection for before/after images in the WAL. `None` (the default) leaves
+    /// the WAL's own passthrough vault in place, so logs stay unencrypted unless the caller
+    /// opts in.
+    pub vault: Option<Arc<dyn Vault>>,
+    /// Compression applied to image payloads ahead of the vault. Defaults to `None`.
+    pub compressor: Compressor,
+    /// How durable a commit must be before `commit_txn` returns. Defaults to `Immediate`.
+    pub durability: Durability,
+    /// Whether concurrent commits share a single `sync_data()` instead of each doing their
+    /// own. Only has an effect under `Durability::Immediate`. Defaults to off.
+    pub group_commit: bool,
 }
 
 impl Default for DatabaseConfig {
@@ -11,6 +27,10 @@ impl Default for DatabaseConfig {
             page_size: 4096,
             buffer_pool_size: 1000,
             max_wal_size_mb: 64,
+            vault: None,
+            compressor: Compressor::default(),
+            durability: Durability::default(),
+            group_commit: false,
         }
     }
 }
\ No newline at end of file
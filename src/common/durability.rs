@@ -0,0 +1,14 @@
+/// How durable a commit must be before the caller gets control back from `commit_txn`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Durability {
+    /// Don't wait on the log at all - fastest, but a crash can lose transactions that
+    /// committed just before it without ever reaching disk.
+    None,
+    /// Rely on the OS to flush the log file on its own schedule, rather than forcing it.
+    /// `LogManager` handles this the same way as `None`: `commit_txn` doesn't wait on the log.
+    Eventual,
+    /// Force the commit record to disk (`sync_data`) before `commit_txn` returns, so a
+    /// transaction the caller sees as committed is guaranteed to survive a crash.
+    #[default]
+    Immediate,
+}
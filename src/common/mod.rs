@@ -4,6 +4,15 @@ pub use error::*;
 mod tid;
 pub use tid::TID;
 
+mod config;
+pub use config::DatabaseConfig;
+
+mod vault;
+pub use vault::{default_vault, Compressor, NoopVault, Vault};
+
+mod durability;
+pub use durability::Durability;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct PageID(pub u64);
 
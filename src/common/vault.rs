@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use crate::common::{BuzzDBError, Result};
+
+/// Optional at-rest protection for the before/after images `LogManager` writes into update
+/// records. Encryption is applied after compression, so a `Vault` only ever sees (and
+/// returns) opaque bytes - it doesn't need to know anything about log record layout.
+pub trait Vault: Send + Sync {
+    fn encrypt(&self, plaintext: &[u8]) -> Vec<u8>;
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Default vault: a straight passthrough, so images stay unencrypted unless a real `Vault`
+/// is configured.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopVault;
+
+impl Vault for NoopVault {
+    fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        plaintext.to_vec()
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        Ok(ciphertext.to_vec())
+    }
+}
+
+/// Returns the default vault - an `Arc<dyn Vault>` so it can be shared across `LogManager`
+/// clones/threads without the caller having to know the concrete type.
+pub fn default_vault() -> Arc<dyn Vault> {
+    Arc::new(NoopVault)
+}
+
+/// Compression applied to an image payload ahead of (optional) encryption. `None` leaves
+/// the payload untouched; the others trade CPU for a smaller on-disk WAL.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compressor {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl Compressor {
+    pub fn compress(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Compressor::None => data.to_vec(),
+            Compressor::Lz4 => lz4_flex::compress_prepend_size(data),
+            Compressor::Zstd => zstd::encode_all(data, 0).expect("zstd compression failed"),
+        }
+    }
+
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compressor::None => Ok(data.to_vec()),
+            Compressor::Lz4 => lz4_flex::decompress_size_prepended(data)
+                .map_err(|_| BuzzDBError::DeserializationError),
+            Compressor::Zstd => {
+                zstd::decode_all(data).map_err(|_| BuzzDBError::DeserializationError)
+            }
+        }
+    }
+}
@@ -6,7 +6,7 @@ pub mod storage;
 pub mod transaction;
 
 pub use buffer::BufferManager;
-pub use common::{PageID, TransactionID, Result};
+pub use common::{DatabaseConfig, PageID, Result, TransactionID};
 pub use log_mod::LogManager;
 pub use transaction::TransactionManager;
 pub use storage::DBFiles;
@@ -24,11 +24,28 @@ pub struct Database {
 
 impl Database {
     pub fn new(db_path: &Path) -> Result<Self> {
+        Self::with_config(db_path, DatabaseConfig::default())
+    }
+
+    /// Same as `new`, but lets the caller configure page/buffer sizing and the WAL's
+    /// vault/compressor instead of taking the defaults.
+    pub fn with_config(db_path: &Path, config: DatabaseConfig) -> Result<Self> {
         let files = DBFiles::new(db_path)?;
-        
-        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(4096, 1000)));
-        let log_manager = Arc::new(Mutex::new(LogManager::new(&files.get_log_file_path())?));
-        
+
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::with_disk_file(
+            &files.get_data_file_path(0),
+            config.page_size,
+            config.buffer_pool_size,
+        )?));
+        let mut log_manager = LogManager::new(&files.get_log_file_path())?
+            .with_compressor(config.compressor)
+            .with_durability(config.durability)
+            .with_group_commit(config.group_commit);
+        if let Some(vault) = config.vault {
+            log_manager = log_manager.with_vault(vault);
+        }
+        let log_manager = Arc::new(Mutex::new(log_manager));
+
         let transaction_manager = TransactionManager::new(
             Arc::clone(&log_manager),
             Arc::clone(&buffer_manager)
@@ -41,7 +58,7 @@ impl Database {
             transaction_manager,
         })
     }
-    
+
     pub fn begin_transaction(&mut self) -> Result<TransactionID> {
         self.transaction_manager.start_txn()
     }
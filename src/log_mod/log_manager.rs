@@ -3,10 +3,13 @@ use std::fs::{File, OpenOptions};
 use std::hash::Hash;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 
 use crate::buffer::BufferManager;
-use crate::common::{BuzzDBError, PageID, Result, TransactionID};
+use crate::common::{
+    default_vault, BuzzDBError, Compressor, Durability, PageID, Result, TransactionID, Vault,
+};
 
 // Log record types - these need to match the on-disk format
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -16,6 +19,9 @@ pub enum LogRecordType {
     AbortRecord = 2,
     UpdateRecord = 3,
     CheckpointRecord = 4,
+    BeginCheckpointRecord = 5,
+    EndCheckpointRecord = 6,
+    CompensationRecord = 7,
 }
 
 impl From<u8> for LogRecordType {
@@ -26,11 +32,34 @@ impl From<u8> for LogRecordType {
             2 => LogRecordType::AbortRecord,
             3 => LogRecordType::UpdateRecord,
             4 => LogRecordType::CheckpointRecord,
+            5 => LogRecordType::BeginCheckpointRecord,
+            6 => LogRecordType::EndCheckpointRecord,
+            7 => LogRecordType::CompensationRecord,
             _ => panic!("Invalid log record type: {}", value),
         }
     }
 }
 
+/// Status of a transaction as recorded in the Active Transaction Table of a fuzzy
+/// checkpoint. Every transaction in the ATT is still running - `Committing` only
+/// distinguishes one that reached its commit record but hadn't finished releasing
+/// locks yet when the checkpoint was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxnStatus {
+    Active = 0,
+    Committing = 1,
+}
+
+impl From<u8> for TxnStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => TxnStatus::Active,
+            1 => TxnStatus::Committing,
+            _ => panic!("Invalid transaction status: {}", value),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LogRecordData {
     pub record_type: LogRecordType,
@@ -42,13 +71,222 @@ pub struct LogRecordData {
     pub after_img: Option<Vec<u8>>,  // After image for redo
     pub log_offset: usize,
     pub record_size: usize,
+    // Populated for `UpdateRecord`/`CompensationRecord` - the LSN of this transaction's
+    // previous log record, chaining its records so undo can walk them without a full scan.
+    pub prev_lsn: Option<u64>,
+    // Only populated for `CompensationRecord` - where undo should resume once this CLR is
+    // seen again, so the update it compensates for is never undone twice.
+    pub undo_next_lsn: Option<u64>,
+    // Only populated for `EndCheckpointRecord`.
+    pub dirty_page_table: Option<HashMap<PageID, u64>>,
+    pub active_transaction_table: Option<HashMap<TransactionID, (u64, TxnStatus)>>,
+}
+
+// Flags byte carried by every `UpdateRecord`, saying whether that record's own images were
+// compressed and/or encrypted, so records written with compression or a vault turned off
+// stay readable next to ones written with it on. The flags don't say which compressor or
+// vault was used - decoding always goes through this manager's current ones.
+const UPDATE_FLAG_COMPRESSED: u8 = 0b01;
+const UPDATE_FLAG_ENCRYPTED: u8 = 0b10;
+
+/// Shared state for group commit: lets concurrent committers coalesce onto a single
+/// `sync_data()` instead of each doing their own. Only takes effect if independent callers
+/// use `append_commit` and `prepare_durability_wait` through the same
+/// `Arc<Mutex<LogManager>>` and drop that lock before `DurabilityWait::wait` - see
+/// `prepare_durability_wait`.
+struct GroupCommit {
+    state: Mutex<GroupCommitState>,
+    cv: Condvar,
+}
+
+struct GroupCommitState {
+    durable_lsn: u64,
+    pending_max_lsn: u64,
+    syncing: bool,
+}
+
+impl GroupCommit {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(GroupCommitState {
+                durable_lsn: 0,
+                pending_max_lsn: 0,
+                syncing: false,
+            }),
+            cv: Condvar::new(),
+        }
+    }
+}
+
+/// What's left of `ensure_durable` once the cheap, lock-requiring part (reading
+/// `Durability`/`group_commit` and cloning a couple of `Arc`s) is done - `wait()` needs no
+/// access to `LogManager` at all, so callers can drop their `Arc<Mutex<LogManager>>` guard
+/// before calling it. That's what actually lets group commit batch: while one caller's
+/// `wait()` is blocked on `sync_data()`, another can still lock `LogManager` to append its
+/// own commit record and join the same wait.
+pub enum DurabilityWait {
+    /// This manager's `Durability` doesn't require waiting on anything.
+    Satisfied,
+    /// No group commit configured - sync this handle directly.
+    Direct {
+        sync_handle: Arc<File>,
+        durable_lsn: Arc<AtomicU64>,
+        lsn: u64,
+    },
+    /// Wait on (or become) the leader syncing on behalf of everyone waiting for `lsn`.
+    Group {
+        group: Arc<GroupCommit>,
+        sync_handle: Arc<File>,
+        lsn: u64,
+    },
+}
+
+impl DurabilityWait {
+    pub fn wait(self) -> Result<()> {
+        match self {
+            DurabilityWait::Satisfied => Ok(()),
+            DurabilityWait::Direct {
+                sync_handle,
+                durable_lsn,
+                lsn,
+            } => {
+                sync_handle.sync_data().map_err(BuzzDBError::IOError)?;
+                durable_lsn.fetch_max(lsn, Ordering::SeqCst);
+                Ok(())
+            }
+            DurabilityWait::Group {
+                group,
+                sync_handle,
+                lsn,
+            } => {
+                let mut state = group.state.lock().unwrap();
+                if state.durable_lsn >= lsn {
+                    return Ok(());
+                }
+                state.pending_max_lsn = state.pending_max_lsn.max(lsn);
+
+                // Wait out any sync already in flight; if it didn't cover `lsn`, become the
+                // next leader instead of waiting on a sync nobody will start.
+                while state.syncing && state.durable_lsn < lsn {
+                    state = group.cv.wait(state).unwrap();
+                }
+                if state.durable_lsn >= lsn {
+                    return Ok(());
+                }
+
+                state.syncing = true;
+                let batch_lsn = state.pending_max_lsn;
+                drop(state);
+
+                let result = sync_handle.sync_data().map_err(BuzzDBError::IOError);
+
+                let mut state = group.state.lock().unwrap();
+                state.syncing = false;
+                if result.is_ok() {
+                    state.durable_lsn = state.durable_lsn.max(batch_lsn);
+                }
+                drop(state);
+                group.cv.notify_all();
+
+                result
+            }
+        }
+    }
+}
+
+/// A page store ARIES recovery can redo/undo against - implemented by both `BufferManager`
+/// and `HeapSegment` so `recovery`/`undo_to` cover whichever one a transaction actually wrote
+/// through, instead of only ever touching `BufferManager`.
+///
+/// A `page_id` this store doesn't hold (e.g. it belongs to a different registered store)
+/// should surface as `Err` from every method here - `redo_phase`/`undo_phase` treat that as
+/// "not mine, skip" rather than as corruption. `PageStores` picks its store with `owns`.
+pub trait RecoverablePageStore {
+    /// Definitive, I/O-free check of whether `page_id` belongs to this store. `PageStores`
+    /// uses this - not a probing call to `page_lsn` - to pick which store to dispatch to, so
+    /// a real load failure from the store that actually owns `page_id` surfaces as its own
+    /// `Err` instead of being swallowed as "not mine" and silently retried against the next
+    /// (possibly catch-all) store.
+    fn owns(&self, page_id: PageID) -> bool;
+
+    /// Current page_lsn for `page_id`, loading it into the store if it isn't cached already.
+    fn page_lsn(&mut self, page_id: PageID) -> Result<u64>;
+
+    /// Overwrites `patch.len()` bytes at `offset` within `page_id`'s serialized
+    /// representation. Does not touch the page's page_lsn - callers that need that updated
+    /// (redo does, undo doesn't) call `set_page_lsn` themselves.
+    fn apply_patch(&mut self, page_id: PageID, offset: u64, patch: &[u8]) -> Result<()>;
+
+    /// Stamps `lsn` as `page_id`'s new page_lsn.
+    fn set_page_lsn(&mut self, page_id: PageID, lsn: u64) -> Result<()>;
+}
+
+/// Dispatches to whichever registered store actually owns `page_id`, trying each in turn.
+/// Exists so `recovery`/`undo_to` can walk a transaction's `prev_lsn` chain exactly once, in
+/// the order records were written - running the walk separately per store would let one
+/// store's compensation records shortcut past the other store's still-undone updates via
+/// `undo_next_lsn`, since a CLR's `undo_next_lsn` assumes everything between it and its
+/// target was already undone.
+pub struct PageStores<'a> {
+    stores: Vec<&'a mut dyn RecoverablePageStore>,
+}
+
+impl<'a> PageStores<'a> {
+    pub fn new(stores: Vec<&'a mut dyn RecoverablePageStore>) -> Self {
+        Self { stores }
+    }
+}
+
+impl<'a> RecoverablePageStore for PageStores<'a> {
+    fn owns(&self, page_id: PageID) -> bool {
+        self.stores.iter().any(|store| store.owns(page_id))
+    }
+
+    fn page_lsn(&mut self, page_id: PageID) -> Result<u64> {
+        for store in self.stores.iter_mut() {
+            if store.owns(page_id) {
+                return store.page_lsn(page_id);
+            }
+        }
+        Err(BuzzDBError::PageNotFound(page_id.0))
+    }
+
+    fn apply_patch(&mut self, page_id: PageID, offset: u64, patch: &[u8]) -> Result<()> {
+        for store in self.stores.iter_mut() {
+            if store.owns(page_id) {
+                return store.apply_patch(page_id, offset, patch);
+            }
+        }
+        Err(BuzzDBError::PageNotFound(page_id.0))
+    }
+
+    fn set_page_lsn(&mut self, page_id: PageID, lsn: u64) -> Result<()> {
+        for store in self.stores.iter_mut() {
+            if store.owns(page_id) {
+                return store.set_page_lsn(page_id, lsn);
+            }
+        }
+        Err(BuzzDBError::PageNotFound(page_id.0))
+    }
 }
 
 pub struct LogManager {
     log_file: File,
+    // Independent handle onto the same underlying file, used only to call sync_data() from
+    // `DurabilityWait::wait` - fsync-ing through this instead of `log_file` means a caller
+    // blocked on durability never needs `&mut self`, so it can't hold the LogManager lock
+    // across the wait.
+    sync_handle: Arc<File>,
     current_offset: usize, // Current write position in the log
     record_counts: HashMap<LogRecordType, u64>,
     txn_id_to_first_log_record: HashMap<TransactionID, usize>, // For rollback
+    txn_id_to_last_lsn: HashMap<TransactionID, u64>, // Tail of each transaction's prev_lsn chain
+    vault: Arc<dyn Vault>, // At-rest protection for update record images; defaults to a passthrough
+    vault_enabled: bool,   // Whether a real vault was configured (vs. the default passthrough)
+    compressor: Compressor, // Compression for update record images, applied before the vault
+    durability: Durability, // How durable a commit must be before log_commit returns
+    durable_lsn: Arc<AtomicU64>, // Highest LSN known synced, when group commit isn't in use
+    group_commit: Option<Arc<GroupCommit>>, // Set by with_group_commit to batch concurrent syncs
 }
 
 impl LogManager {
@@ -60,19 +298,115 @@ impl LogManager {
             .open(log_file_path)
             .map_err(BuzzDBError::IOError)?;
 
+        // Reopening a non-empty WAL must resume writing (and let read_all_logs see) past
+        // whatever was already on disk - starting from 0 here would make every prior record
+        // invisible to recovery on the very next restart.
+        let current_offset = log_file.metadata().map_err(BuzzDBError::IOError)?.len() as usize;
+        let sync_handle = Arc::new(log_file.try_clone().map_err(BuzzDBError::IOError)?);
+
         Ok(Self {
             log_file,
-            current_offset: 0,
+            sync_handle,
+            current_offset,
             record_counts: HashMap::new(),
             txn_id_to_first_log_record: HashMap::new(),
+            txn_id_to_last_lsn: HashMap::new(),
+            vault: default_vault(),
+            vault_enabled: false,
+            compressor: Compressor::default(),
+            durability: Durability::default(),
+            durable_lsn: Arc::new(AtomicU64::new(0)),
+            group_commit: None,
         })
     }
 
+    /// Configures the vault used to encrypt/decrypt update record images. Builder-style so
+    /// callers can opt in without disturbing the plain `new` path most tests use.
+    pub fn with_vault(mut self, vault: Arc<dyn Vault>) -> Self {
+        self.vault = vault;
+        self.vault_enabled = true;
+        self
+    }
+
+    /// Configures the compression applied to update record images ahead of the vault.
+    pub fn with_compressor(mut self, compressor: Compressor) -> Self {
+        self.compressor = compressor;
+        self
+    }
+
+    /// Configures how durable `log_commit` makes a transaction before returning.
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Turns on group commit: concurrent callers share a single `sync_data()` instead of
+    /// each doing their own. Only meaningful under `Durability::Immediate`.
+    pub fn with_group_commit(mut self, enabled: bool) -> Self {
+        self.group_commit = if enabled {
+            Some(Arc::new(GroupCommit::new()))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// The highest LSN known durable - everything at or before it is guaranteed to survive
+    /// a crash. Lets callers reason about what a crash right now would lose.
+    pub fn last_durable_lsn(&self) -> u64 {
+        match &self.group_commit {
+            Some(group) => group.state.lock().unwrap().durable_lsn,
+            None => self.durable_lsn.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Figures out what `lsn` being durable requires under this manager's configured
+    /// `Durability`, without doing any of the actual waiting - that's `DurabilityWait::wait`,
+    /// which needs no access to `LogManager` at all. Callers that want group commit to
+    /// actually batch must drop their `Arc<Mutex<LogManager>>` guard between calling this
+    /// and calling `.wait()`; calling both under one guard (as `ensure_durable` does) is no
+    /// better than before the split, since nobody else can append while this blocks.
+    pub fn prepare_durability_wait(&mut self, lsn: u64) -> Result<DurabilityWait> {
+        if self.durability != Durability::Immediate {
+            return Ok(DurabilityWait::Satisfied);
+        }
+
+        Ok(match &self.group_commit {
+            Some(group) => DurabilityWait::Group {
+                group: Arc::clone(group),
+                sync_handle: Arc::clone(&self.sync_handle),
+                lsn,
+            },
+            None => DurabilityWait::Direct {
+                sync_handle: Arc::clone(&self.sync_handle),
+                durable_lsn: Arc::clone(&self.durable_lsn),
+                lsn,
+            },
+        })
+    }
+
+    /// Makes sure `lsn` is durable per this manager's configured `Durability`, blocking right
+    /// here under whatever lock the caller holds on `LogManager`. Fine for callers that don't
+    /// need group commit to batch; `TransactionManager::commit_txn` instead calls
+    /// `prepare_durability_wait` and `.wait()` as separate steps so it can drop its lock
+    /// in between.
+    pub fn ensure_durable(&mut self, lsn: u64) -> Result<()> {
+        self.prepare_durability_wait(lsn)?.wait()
+    }
+
     pub fn reset(&mut self, log_file: File) -> Result<()> {
+        self.sync_handle = Arc::new(log_file.try_clone().map_err(BuzzDBError::IOError)?);
         self.log_file = log_file;
         self.current_offset = 0;
         self.txn_id_to_first_log_record.clear();
+        self.txn_id_to_last_lsn.clear();
         self.record_counts.clear();
+        self.durable_lsn.store(0, Ordering::SeqCst);
+        if let Some(group) = &self.group_commit {
+            let mut state = group.state.lock().unwrap();
+            state.durable_lsn = 0;
+            state.pending_max_lsn = 0;
+        }
         Ok(())
     }
 
@@ -89,40 +423,62 @@ impl LogManager {
         Ok(())
     }
 
-    pub fn log_txn_begin(&mut self, txn_id: TransactionID) -> Result<()> {
-        let record_type = [LogRecordType::BeginRecord as u8];
-        self.write_to_log(&record_type)?;
+    /// Appends one framed record - `[length: u64 LE][type byte][body][crc32: u32 LE]` - and
+    /// returns its LSN (the offset the length prefix starts at). The length and trailing
+    /// checksum are what let `read_all_logs` detect a record torn by a mid-append crash and
+    /// stop there instead of parsing garbage past it.
+    fn write_record(&mut self, record_type: LogRecordType, body: &[u8]) -> Result<u64> {
+        let lsn = self.current_offset as u64;
 
-        let txn_id_bytes = txn_id.0.to_le_bytes();
-        self.write_to_log(&txn_id_bytes)?;
+        let mut payload = Vec::with_capacity(1 + body.len());
+        payload.push(record_type as u8);
+        payload.extend_from_slice(body);
+        let checksum = crc32fast::hash(&payload);
 
-        *self
-            .record_counts
-            .entry(LogRecordType::BeginRecord)
-            .or_insert(0) += 1;
+        self.write_to_log(&(payload.len() as u64).to_le_bytes())?;
+        self.write_to_log(&payload)?;
+        self.write_to_log(&checksum.to_le_bytes())?;
 
-        // Track where this transaction's log records start (for rollback)
-        self.txn_id_to_first_log_record
-            .insert(txn_id, self.current_offset - std::mem::size_of::<u64>() - 1);
+        *self.record_counts.entry(record_type).or_insert(0) += 1;
 
-        Ok(())
+        Ok(lsn)
     }
 
-    pub fn log_commit(&mut self, txn_id: TransactionID) -> Result<()> {
-        let record_type = [LogRecordType::CommitRecord as u8];
-        self.write_to_log(&record_type)?;
+    pub fn log_txn_begin(&mut self, txn_id: TransactionID) -> Result<()> {
+        let lsn = self.write_record(LogRecordType::BeginRecord, &txn_id.0.to_le_bytes())?;
 
-        let txn_id_bytes = txn_id.0.to_le_bytes();
-        self.write_to_log(&txn_id_bytes)?;
+        // Track where this transaction's log records start (for rollback)
+        self.txn_id_to_first_log_record.insert(txn_id, lsn as usize);
+        // The begin record anchors the head of this transaction's prev_lsn chain.
+        self.txn_id_to_last_lsn.insert(txn_id, lsn);
+
+        Ok(())
+    }
 
-        *self
-            .record_counts
-            .entry(LogRecordType::CommitRecord)
-            .or_insert(0) += 1;
+    /// Appends a commit record and returns its LSN, without waiting for it to become
+    /// durable. Pairs with `prepare_durability_wait`/`DurabilityWait::wait` - a caller that
+    /// wants group commit's batching to actually kick in should call `append_commit`, then
+    /// `prepare_durability_wait`, then drop its lock on this manager before calling `.wait()`
+    /// (see `TransactionManager::commit_txn`), rather than calling `log_commit`, which holds
+    /// one lock across appending, syncing, and waiting.
+    pub fn append_commit(&mut self, txn_id: TransactionID) -> Result<u64> {
+        let lsn =
+            self.write_record(LogRecordType::CommitRecord, &txn_id.0.to_le_bytes())?;
 
         self.txn_id_to_first_log_record.remove(&txn_id);
+        self.txn_id_to_last_lsn.remove(&txn_id);
 
-        Ok(())
+        Ok(lsn)
+    }
+
+    /// Appends a commit record and, per this manager's `Durability`, makes sure it's durable
+    /// before returning - under `Immediate` that means `log_commit` itself doesn't return
+    /// until `sync_data()` covers this record. Holds one lock across both steps, so group
+    /// commit's batching never kicks in here even if enabled - see `append_commit` for the
+    /// split version that lets it.
+    pub fn log_commit(&mut self, txn_id: TransactionID) -> Result<()> {
+        let lsn = self.append_commit(txn_id)?;
+        self.ensure_durable(lsn)
     }
 
     pub fn log_abort(
@@ -130,16 +486,7 @@ impl LogManager {
         txn_id: TransactionID,
         buffer_manager: &mut BufferManager,
     ) -> Result<()> {
-        let record_type = [LogRecordType::AbortRecord as u8];
-        self.write_to_log(&record_type)?;
-
-        let txn_id_bytes = txn_id.0.to_le_bytes();
-        self.write_to_log(&txn_id_bytes)?;
-
-        *self
-            .record_counts
-            .entry(LogRecordType::AbortRecord)
-            .or_insert(0) += 1;
+        self.write_record(LogRecordType::AbortRecord, &txn_id.0.to_le_bytes())?;
 
         // Actually perform the rollback by undoing changes
         self.rollback_txn(txn_id, buffer_manager)?;
@@ -149,6 +496,18 @@ impl LogManager {
         Ok(())
     }
 
+    /// Appends an update record and returns its LSN (the log offset it starts at), which
+    /// the caller stamps onto the page it just changed so the WAL invariant can be enforced
+    /// before that page is ever written back to disk. The record also carries `prev_lsn`,
+    /// chaining it to this transaction's previous log record so undo can walk the chain
+    /// instead of scanning the whole log.
+    ///
+    /// Each image is compressed and/or encrypted per this manager's `compressor`/`vault`
+    /// before being written, with a one-byte flags field recording which was applied - so
+    /// turning compression or the vault on or off partway through a log leaves earlier
+    /// records readable (decoding uses this manager's current compressor and vault), and
+    /// `length` itself always refers to the original, decoded image size used to patch the
+    /// page during redo/undo.
     pub fn log_update(
         &mut self,
         txn_id: TransactionID,
@@ -157,60 +516,159 @@ impl LogManager {
         offset: u64,
         before_img: &[u8],
         after_img: &[u8],
-    ) -> Result<()> {
-        let record_type = [LogRecordType::UpdateRecord as u8];
-        self.write_to_log(&record_type)?;
+    ) -> Result<u64> {
+        let prev_lsn = *self.txn_id_to_last_lsn.get(&txn_id).unwrap_or(&0);
 
-        let txn_id_bytes = txn_id.0.to_le_bytes();
-        let page_id_bytes = page_id.0.to_le_bytes();
-        let length_bytes = length.to_le_bytes();
-        let offset_bytes = offset.to_le_bytes();
+        let mut flags = 0u8;
+        if self.compressor != Compressor::None {
+            flags |= UPDATE_FLAG_COMPRESSED;
+        }
+        if self.vault_enabled {
+            flags |= UPDATE_FLAG_ENCRYPTED;
+        }
 
-        self.write_to_log(&txn_id_bytes)?;
-        self.write_to_log(&page_id_bytes)?;
-        self.write_to_log(&length_bytes)?;
-        self.write_to_log(&offset_bytes)?;
+        let before_encoded = self.vault.encrypt(&self.compressor.compress(before_img));
+        let after_encoded = self.vault.encrypt(&self.compressor.compress(after_img));
+
+        let mut body =
+            Vec::with_capacity(41 + 16 + before_encoded.len() + after_encoded.len());
+        body.extend_from_slice(&txn_id.0.to_le_bytes());
+        body.extend_from_slice(&page_id.0.to_le_bytes());
+        body.extend_from_slice(&length.to_le_bytes());
+        body.extend_from_slice(&offset.to_le_bytes());
+        body.extend_from_slice(&prev_lsn.to_le_bytes());
+        body.push(flags);
+        body.extend_from_slice(&(before_encoded.len() as u64).to_le_bytes());
+        body.extend_from_slice(&before_encoded);
+        body.extend_from_slice(&(after_encoded.len() as u64).to_le_bytes());
+        body.extend_from_slice(&after_encoded);
+
+        let lsn = self.write_record(LogRecordType::UpdateRecord, &body)?;
+        self.txn_id_to_last_lsn.insert(txn_id, lsn);
+
+        Ok(lsn)
+    }
 
-        self.write_to_log(before_img)?;
-        self.write_to_log(after_img)?;
+    /// Writes a Compensation Log Record (CLR) for an undo action: `redo_img` (the image
+    /// being restored, i.e. the undone update's before-image) is this record's own redo
+    /// payload, and `undo_next_lsn` is the undone record's `prev_lsn` - where undo should
+    /// resume if a crash interrupts recovery right after this CLR makes it to disk, so the
+    /// same update is never undone twice.
+    pub fn log_compensation(
+        &mut self,
+        txn_id: TransactionID,
+        page_id: PageID,
+        offset: u64,
+        redo_img: &[u8],
+        undo_next_lsn: u64,
+    ) -> Result<u64> {
+        let prev_lsn = *self.txn_id_to_last_lsn.get(&txn_id).unwrap_or(&0);
+
+        let mut body = Vec::with_capacity(48 + redo_img.len());
+        body.extend_from_slice(&txn_id.0.to_le_bytes());
+        body.extend_from_slice(&page_id.0.to_le_bytes());
+        body.extend_from_slice(&(redo_img.len() as u64).to_le_bytes());
+        body.extend_from_slice(&offset.to_le_bytes());
+        body.extend_from_slice(&prev_lsn.to_le_bytes());
+        body.extend_from_slice(&undo_next_lsn.to_le_bytes());
+        body.extend_from_slice(redo_img);
+
+        let lsn = self.write_record(LogRecordType::CompensationRecord, &body)?;
+        self.txn_id_to_last_lsn.insert(txn_id, lsn);
+
+        Ok(lsn)
+    }
 
-        *self
-            .record_counts
-            .entry(LogRecordType::UpdateRecord)
-            .or_insert(0) += 1;
+    /// WAL rule: guarantees the log is durable at least up to `page_lsn` before the caller
+    /// is allowed to write the corresponding page to disk. Every write already goes straight
+    /// through to the file, so this only needs to force the OS's buffers out.
+    pub fn flush_until(&mut self, page_lsn: u64) -> Result<()> {
+        if page_lsn as usize > self.current_offset {
+            return Err(BuzzDBError::Other(format!(
+                "Cannot flush past the end of the log: requested LSN {}, log ends at {}",
+                page_lsn, self.current_offset
+            )));
+        }
 
-        Ok(())
+        self.log_file.sync_data().map_err(BuzzDBError::IOError)
     }
 
-    pub fn log_checkpoint(&mut self, _buffer_manager: &BufferManager) -> Result<()> {
-        let record_type = [LogRecordType::CheckpointRecord as u8];
-        self.write_to_log(&record_type)?;
+    /// Fuzzy checkpoint, part one: a marker record written just ahead of
+    /// `log_end_checkpoint`. Writing this doesn't require quiescing active transactions.
+    /// Recovery's analysis pass reads only the end-checkpoint record, not this one.
+    pub fn log_begin_checkpoint(&mut self) -> Result<u64> {
+        self.write_record(
+            LogRecordType::BeginCheckpointRecord,
+            &TransactionID(0).0.to_le_bytes(),
+        )
+    }
 
-        // TODO: Write dirty page table and active transaction table
-        *self
-            .record_counts
-            .entry(LogRecordType::CheckpointRecord)
-            .or_insert(0) += 1;
+    /// Fuzzy checkpoint, part two: persists the Dirty Page Table (each dirty page's
+    /// `recLSN`, the LSN of the first update that dirtied it since it was last clean) and
+    /// the Active Transaction Table (each live transaction's `lastLSN` and status), both
+    /// snapshotted while transactions kept running - hence "fuzzy".
+    pub fn log_end_checkpoint(
+        &mut self,
+        dirty_page_table: &HashMap<PageID, u64>,
+        active_transaction_table: &HashMap<TransactionID, (u64, TxnStatus)>,
+    ) -> Result<u64> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&TransactionID(0).0.to_le_bytes());
+
+        body.extend_from_slice(&(dirty_page_table.len() as u64).to_le_bytes());
+        for (&page_id, &rec_lsn) in dirty_page_table {
+            body.extend_from_slice(&page_id.0.to_le_bytes());
+            body.extend_from_slice(&rec_lsn.to_le_bytes());
+        }
 
-        Ok(())
+        body.extend_from_slice(&(active_transaction_table.len() as u64).to_le_bytes());
+        for (&txn_id, &(last_lsn, status)) in active_transaction_table {
+            body.extend_from_slice(&txn_id.0.to_le_bytes());
+            body.extend_from_slice(&last_lsn.to_le_bytes());
+            body.push(status as u8);
+        }
+
+        self.write_record(LogRecordType::EndCheckpointRecord, &body)
     }
 
-    pub fn recovery(&mut self, buffer_manager: &mut BufferManager) -> Result<()> {
+    /// Drives the redo/undo passes against `store` - pass a `PageStores` wrapping every
+    /// store a transaction might have written through (e.g. the buffer manager plus every
+    /// registered heap segment) so a transaction whose updates span more than one of them
+    /// gets recovered in full rather than just the half that happens to be `BufferManager`.
+    pub fn recovery<S: RecoverablePageStore>(&mut self, store: &mut S) -> Result<()> {
         let logs = self.read_all_logs()?;
 
         // Classic ARIES three-phase recovery
-        let (active_txns, committed_txns, aborted_txns) = self.analysis_phase(&logs);
+        let (active_txns, committed_txns, aborted_txns, dirty_page_table, last_lsn_per_txn) =
+            self.analysis_phase(&logs);
 
-        self.redo_phase(&logs, &committed_txns, &active_txns, buffer_manager)?;
+        self.redo_phase(
+            &logs,
+            &committed_txns,
+            &active_txns,
+            &dirty_page_table,
+            store,
+        )?;
 
         // Undo all transactions that didn't commit
         let transactions_to_undo: HashSet<TransactionID> =
             active_txns.union(&aborted_txns).cloned().collect();
-        self.undo_phase(&logs, &transactions_to_undo, buffer_manager)?;
+        self.undo_phase(
+            &logs,
+            &transactions_to_undo,
+            &last_lsn_per_txn,
+            store,
+        )?;
 
         Ok(())
     }
 
+    /// Analysis pass: rebuilds the Active Transaction Table from the whole log (cheap -
+    /// this is just bookkeeping, not page I/O), but seeds it from the most recent
+    /// end-checkpoint record instead of starting cold, and hands back that checkpoint's
+    /// Dirty Page Table so `redo_phase` knows where it can skip ahead to. Also tracks, per
+    /// transaction, the LSN of its last log record - the head `undo_phase` starts walking
+    /// each loser's `prev_lsn` chain from.
     fn analysis_phase(
         &self,
         logs: &[LogRecordData],
@@ -218,15 +676,36 @@ impl LogManager {
         HashSet<TransactionID>,
         HashSet<TransactionID>,
         HashSet<TransactionID>,
+        HashMap<PageID, u64>,
+        HashMap<TransactionID, u64>,
     ) {
         let mut active_txns = HashSet::new();
         let mut committed_txns = HashSet::new();
         let mut aborted_txns = HashSet::new();
+        let mut dirty_page_table = HashMap::new();
+        let mut last_lsn_per_txn = HashMap::new();
+
+        if let Some(checkpoint) = logs
+            .iter()
+            .rev()
+            .find(|log| log.record_type == LogRecordType::EndCheckpointRecord)
+        {
+            if let Some(att) = &checkpoint.active_transaction_table {
+                active_txns.extend(att.keys().copied());
+                for (&txn_id, &(last_lsn, _status)) in att {
+                    last_lsn_per_txn.insert(txn_id, last_lsn);
+                }
+            }
+            if let Some(dpt) = &checkpoint.dirty_page_table {
+                dirty_page_table = dpt.clone();
+            }
+        }
 
         for log in logs {
             match log.record_type {
                 LogRecordType::BeginRecord => {
                     active_txns.insert(log.txn_id);
+                    last_lsn_per_txn.insert(log.txn_id, log.log_offset as u64);
                 }
                 LogRecordType::CommitRecord => {
                     active_txns.remove(&log.txn_id);
@@ -236,79 +715,233 @@ impl LogManager {
                     active_txns.remove(&log.txn_id);
                     aborted_txns.insert(log.txn_id);
                 }
+                LogRecordType::UpdateRecord => {
+                    if let Some(page_id) = log.page_id {
+                        dirty_page_table
+                            .entry(page_id)
+                            .or_insert(log.log_offset as u64);
+                    }
+                    last_lsn_per_txn.insert(log.txn_id, log.log_offset as u64);
+                }
+                LogRecordType::CompensationRecord => {
+                    if let Some(page_id) = log.page_id {
+                        dirty_page_table
+                            .entry(page_id)
+                            .or_insert(log.log_offset as u64);
+                    }
+                    last_lsn_per_txn.insert(log.txn_id, log.log_offset as u64);
+                }
                 _ => {}
             }
         }
 
-        (active_txns, committed_txns, aborted_txns)
+        (
+            active_txns,
+            committed_txns,
+            aborted_txns,
+            dirty_page_table,
+            last_lsn_per_txn,
+        )
     }
 
-    fn redo_phase(
+    fn redo_phase<S: RecoverablePageStore>(
         &self,
         logs: &[LogRecordData],
         committed_txns: &HashSet<TransactionID>,
         active_txns: &HashSet<TransactionID>,
-        buffer_manager: &mut BufferManager,
+        dirty_page_table: &HashMap<PageID, u64>,
+        store: &mut S,
     ) -> Result<()> {
+        // Nothing before the oldest recLSN in the DPT can possibly need redoing - every
+        // page was clean at or before that point, so the updates that produced that state
+        // already made it to disk.
+        let redo_start_lsn = dirty_page_table.values().copied().min().unwrap_or(0);
+
         for log in logs {
-            if log.record_type == LogRecordType::UpdateRecord {
-                // Only redo committed transactions and active ones (they might have committed)
-                if committed_txns.contains(&log.txn_id) || active_txns.contains(&log.txn_id) {
-                    if let (Some(page_id), Some(offset), Some(after_img), Some(length)) =
-                        (log.page_id, log.offset, &log.after_img, log.length)
-                    {
-                        let frame = buffer_manager.fix_page(page_id, true)?;
-                        {
-                            let mut frame_guard = frame.lock().unwrap();
-                            let data = frame_guard.get_data_mut();
+            if (log.log_offset as u64) < redo_start_lsn {
+                continue;
+            }
 
-                            // Apply the "after" image to redo the change
-                            data[offset as usize..offset as usize + length as usize]
-                                .copy_from_slice(&after_img[0..length as usize]);
-                        }
+            // CLRs are always redone regardless of the owning transaction's eventual
+            // status - the undo they record already happened and must never be lost.
+            // Plain updates only redo if the transaction might have committed.
+            let eligible = match log.record_type {
+                LogRecordType::UpdateRecord => {
+                    committed_txns.contains(&log.txn_id) || active_txns.contains(&log.txn_id)
+                }
+                LogRecordType::CompensationRecord => true,
+                _ => false,
+            };
+            if !eligible {
+                continue;
+            }
 
-                        buffer_manager.unfix_page(Arc::clone(&frame), true)?;
-                    }
+            let (page_id, offset, redo_img, length) =
+                match (log.page_id, log.offset, &log.after_img, log.length) {
+                    (Some(p), Some(o), Some(img), Some(l)) => (p, o, img, l),
+                    _ => continue,
+                };
+
+            // A recLSN greater than this record's LSN means the page was still clean when
+            // this update happened, i.e. it was already durable by the time of the
+            // checkpoint - redoing it again would be harmless but wasted work, so skip it.
+            if let Some(&rec_lsn) = dirty_page_table.get(&page_id) {
+                if (log.log_offset as u64) < rec_lsn {
+                    continue;
                 }
             }
+
+            // A page_id this store doesn't own - it belongs to a different registered store -
+            // surfaces as an error here. Recovery drives every store over the same shared
+            // log, so skip it rather than fail the whole pass.
+            let current_lsn = match store.page_lsn(page_id) {
+                Ok(lsn) => lsn,
+                Err(_) => continue,
+            };
+
+            // Idempotent redo: a page already at or past this record's LSN already
+            // reflects it, whether from an earlier run or an interrupted recovery pass.
+            if current_lsn >= log.log_offset as u64 {
+                continue;
+            }
+
+            store.apply_patch(page_id, offset, &redo_img[0..length as usize])?;
+            store.set_page_lsn(page_id, log.log_offset as u64)?;
         }
 
         Ok(())
     }
 
-    fn undo_phase(
-        &self,
+    /// Undoes every loser transaction by walking its `prev_lsn` chain backward from its
+    /// last log record instead of rescanning the whole log. Each undone update is logged as
+    /// a CLR before moving on, so a crash partway through undo resumes from the CLR's
+    /// `undo_next_lsn` on the next recovery attempt rather than undoing the same update twice.
+    fn undo_phase<S: RecoverablePageStore>(
+        &mut self,
         logs: &[LogRecordData],
         transactions_to_undo: &HashSet<TransactionID>,
-        buffer_manager: &mut BufferManager,
+        last_lsn_per_txn: &HashMap<TransactionID, u64>,
+        store: &mut S,
     ) -> Result<()> {
-        // Process in reverse order to undo changes
-        for log in logs.iter().rev() {
-            if transactions_to_undo.contains(&log.txn_id) {
-                if log.record_type == LogRecordType::UpdateRecord {
+        let by_lsn: HashMap<u64, &LogRecordData> =
+            logs.iter().map(|log| (log.log_offset as u64, log)).collect();
+
+        for &txn_id in transactions_to_undo {
+            let mut next_lsn = last_lsn_per_txn.get(&txn_id).copied();
+
+            while let Some(lsn) = next_lsn {
+                let log = match by_lsn.get(&lsn) {
+                    Some(&log) => log,
+                    None => break,
+                };
+
+                match log.record_type {
+                    LogRecordType::UpdateRecord => {
+                        if let (Some(page_id), Some(offset), Some(before_img), Some(length)) =
+                            (log.page_id, log.offset, &log.before_img, log.length)
+                        {
+                            // A page `store` can't load is left as-is and gets no CLR.
+                            if store.page_lsn(page_id).is_ok() {
+                                store.apply_patch(
+                                    page_id,
+                                    offset,
+                                    &before_img[0..length as usize],
+                                )?;
+
+                                self.log_compensation(
+                                    txn_id,
+                                    page_id,
+                                    offset,
+                                    before_img,
+                                    log.prev_lsn.unwrap_or(0),
+                                )?;
+                            }
+                        }
+                        next_lsn = log.prev_lsn;
+                    }
+                    LogRecordType::CompensationRecord => {
+                        // This undo already happened in a prior recovery attempt - resume
+                        // from where it left off without redoing the undo itself.
+                        next_lsn = log.undo_next_lsn;
+                    }
+                    LogRecordType::BeginRecord => {
+                        // Hit the beginning of the transaction - we're done.
+                        next_lsn = None;
+                    }
+                    _ => {
+                        next_lsn = None;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Partial rollback for a savepoint: undoes every update this transaction logged after
+    /// `stop_lsn` (exclusive), walking its `prev_lsn` chain backward from its current tail
+    /// the same way `undo_phase` does, just bounded to one transaction and stopping at
+    /// `stop_lsn` instead of running all the way to the transaction's `BeginRecord`. Each
+    /// undone update is logged as a CLR, so a crash mid-rollback is recovered exactly like
+    /// any other interrupted undo. Returns the transaction's new last LSN (the tail of its
+    /// `prev_lsn` chain after the CLRs), which the caller should feed back into its own LSN
+    /// tracking for this transaction.
+    ///
+    /// Pass a `PageStores` wrapping every store the transaction might have written through -
+    /// the chain is walked exactly once here, so splitting it across several calls (one per
+    /// store) would let a CLR written in one call shortcut past updates the other store
+    /// still needs undone.
+    pub fn undo_to<S: RecoverablePageStore>(
+        &mut self,
+        txn_id: TransactionID,
+        stop_lsn: u64,
+        store: &mut S,
+    ) -> Result<u64> {
+        let logs = self.read_all_logs()?;
+        let by_lsn: HashMap<u64, &LogRecordData> =
+            logs.iter().map(|log| (log.log_offset as u64, log)).collect();
+
+        let mut next_lsn = self.txn_id_to_last_lsn.get(&txn_id).copied();
+
+        while let Some(lsn) = next_lsn {
+            if lsn <= stop_lsn {
+                break;
+            }
+
+            let log = match by_lsn.get(&lsn) {
+                Some(&log) => log,
+                None => break,
+            };
+
+            match log.record_type {
+                LogRecordType::UpdateRecord => {
                     if let (Some(page_id), Some(offset), Some(before_img), Some(length)) =
                         (log.page_id, log.offset, &log.before_img, log.length)
                     {
-                        let frame = buffer_manager.fix_page(page_id, true)?;
-                        {
-                            let mut frame_guard = frame.lock().unwrap();
-                            let data = frame_guard.get_data_mut();
-
-                            // Apply the "before" image to undo the change
-                            data[offset as usize..offset as usize + length as usize]
-                                .copy_from_slice(&before_img[0..length as usize]);
+                        // A page `store` can't load is left as-is and gets no CLR.
+                        if store.page_lsn(page_id).is_ok() {
+                            store.apply_patch(page_id, offset, &before_img[0..length as usize])?;
+
+                            self.log_compensation(
+                                txn_id,
+                                page_id,
+                                offset,
+                                before_img,
+                                log.prev_lsn.unwrap_or(0),
+                            )?;
                         }
-
-                        buffer_manager.unfix_page(Arc::clone(&frame), true)?;
                     }
-                } else if log.record_type == LogRecordType::BeginRecord {
-                    // Hit the beginning of the transaction - we're done
-                    break;
+                    next_lsn = log.prev_lsn;
                 }
+                LogRecordType::CompensationRecord => {
+                    // Already undone - resume from where that undo's own chain continues.
+                    next_lsn = log.undo_next_lsn;
+                }
+                _ => next_lsn = None,
             }
         }
 
-        Ok(())
+        Ok(*self.txn_id_to_last_lsn.get(&txn_id).unwrap_or(&stop_lsn))
     }
 
     fn rollback_txn(
@@ -344,6 +977,145 @@ impl LogManager {
         Ok(())
     }
 
+    /// Reverses an update record image's flags byte: decrypt first (a no-op under the
+    /// default vault), then decompress, yielding the original plaintext image so redo/undo
+    /// stays oblivious to whatever encoding this particular record was written with.
+    fn decode_image(&self, encoded: &[u8], flags: u8) -> Result<Vec<u8>> {
+        let decrypted = if flags & UPDATE_FLAG_ENCRYPTED != 0 {
+            self.vault.decrypt(encoded)?
+        } else {
+            encoded.to_vec()
+        };
+
+        if flags & UPDATE_FLAG_COMPRESSED != 0 {
+            self.compressor.decompress(&decrypted)
+        } else {
+            Ok(decrypted)
+        }
+    }
+
+    /// Reconstructs a parsed record from its framed payload (type byte + body, with the
+    /// length prefix and checksum already stripped off by the caller). Returns `None` on
+    /// an unrecognized type byte, a body too short for its record type, or an image that
+    /// fails to decode - either way the caller treats that as corruption and stops reading
+    /// rather than trusting the rest.
+    fn parse_record(&self, payload: &[u8], log_offset: usize) -> Option<LogRecordData> {
+        let &record_type_byte = payload.first()?;
+        if record_type_byte > LogRecordType::CompensationRecord as u8 {
+            return None;
+        }
+        let record_type = LogRecordType::from(record_type_byte);
+
+        let mut cursor = 1usize;
+        let read_u64 = |cursor: &mut usize| -> Option<u64> {
+            let bytes = payload.get(*cursor..*cursor + 8)?;
+            *cursor += 8;
+            Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+        };
+        let read_u8 = |cursor: &mut usize| -> Option<u8> {
+            let byte = *payload.get(*cursor)?;
+            *cursor += 1;
+            Some(byte)
+        };
+
+        let txn_id = TransactionID(read_u64(&mut cursor)?);
+
+        let mut log_record = LogRecordData {
+            record_type,
+            txn_id,
+            page_id: None,
+            length: None,
+            offset: None,
+            before_img: None,
+            after_img: None,
+            log_offset,
+            record_size: 0, // Filled in by the caller, which knows the on-disk framing size.
+            prev_lsn: None,
+            undo_next_lsn: None,
+            dirty_page_table: None,
+            active_transaction_table: None,
+        };
+
+        match record_type {
+            LogRecordType::UpdateRecord => {
+                // Update records have extra data: page_id, length, offset, prev_lsn, a
+                // flags byte describing how the images below are encoded, and the
+                // before/after images themselves (each its own length-prefixed blob,
+                // since compression/encryption can change their size independently).
+                let page_id = PageID(read_u64(&mut cursor)?);
+                let length = read_u64(&mut cursor)?;
+                let record_offset = read_u64(&mut cursor)?;
+                let prev_lsn = read_u64(&mut cursor)?;
+                let flags = read_u8(&mut cursor)?;
+
+                let before_len = read_u64(&mut cursor)? as usize;
+                let before_encoded = payload.get(cursor..cursor + before_len)?;
+                cursor += before_len;
+                let after_len = read_u64(&mut cursor)? as usize;
+                let after_encoded = payload.get(cursor..cursor + after_len)?;
+
+                let before_img = self.decode_image(before_encoded, flags).ok()?;
+                let after_img = self.decode_image(after_encoded, flags).ok()?;
+
+                log_record.page_id = Some(page_id);
+                log_record.length = Some(length);
+                log_record.offset = Some(record_offset);
+                log_record.prev_lsn = Some(prev_lsn);
+                log_record.before_img = Some(before_img);
+                log_record.after_img = Some(after_img);
+            }
+            LogRecordType::CompensationRecord => {
+                // CLRs carry page_id, length, offset, prev_lsn, undo_next_lsn, and a single
+                // redo payload (the before-image of the update they compensate for) - there
+                // is no before-image here since a CLR is never itself undone.
+                let page_id = PageID(read_u64(&mut cursor)?);
+                let length = read_u64(&mut cursor)?;
+                let record_offset = read_u64(&mut cursor)?;
+                let prev_lsn = read_u64(&mut cursor)?;
+                let undo_next_lsn = read_u64(&mut cursor)?;
+
+                let redo_img = payload.get(cursor..cursor + length as usize)?.to_vec();
+
+                log_record.page_id = Some(page_id);
+                log_record.length = Some(length);
+                log_record.offset = Some(record_offset);
+                log_record.prev_lsn = Some(prev_lsn);
+                log_record.undo_next_lsn = Some(undo_next_lsn);
+                log_record.after_img = Some(redo_img);
+            }
+            LogRecordType::EndCheckpointRecord => {
+                let dpt_len = read_u64(&mut cursor)?;
+                let mut dirty_page_table = HashMap::new();
+                for _ in 0..dpt_len {
+                    let page_id = PageID(read_u64(&mut cursor)?);
+                    let rec_lsn = read_u64(&mut cursor)?;
+                    dirty_page_table.insert(page_id, rec_lsn);
+                }
+
+                let att_len = read_u64(&mut cursor)?;
+                let mut active_transaction_table = HashMap::new();
+                for _ in 0..att_len {
+                    let txn_id = TransactionID(read_u64(&mut cursor)?);
+                    let last_lsn = read_u64(&mut cursor)?;
+                    let status = TxnStatus::from(read_u8(&mut cursor)?);
+                    active_transaction_table.insert(txn_id, (last_lsn, status));
+                }
+
+                log_record.dirty_page_table = Some(dirty_page_table);
+                log_record.active_transaction_table = Some(active_transaction_table);
+            }
+            _ => {}
+        }
+
+        Some(log_record)
+    }
+
+    /// Reads every record still intact on disk. Each record is framed as
+    /// `[length: u64 LE][type byte + body][crc32: u32 LE]`; on the first record whose
+    /// length would run past the known end of the log, whose checksum doesn't match, or
+    /// whose body doesn't parse, reading stops and the file is truncated back to the end
+    /// of the last good record. This is what lets recovery run cleanly after a crash that
+    /// left a torn record at the tail instead of aborting on it.
     fn read_all_logs(&self) -> Result<Vec<LogRecordData>> {
         let mut logs = Vec::new();
         let mut offset = 0;
@@ -353,67 +1125,50 @@ impl LogManager {
             .map_err(BuzzDBError::IOError)?;
 
         while offset < self.current_offset {
-            // Read the basic record header (type + transaction ID)
-            let mut record_type_buf = [0u8; 1];
-            file.read_exact(&mut record_type_buf)
-                .map_err(BuzzDBError::IOError)?;
-            let record_type = LogRecordType::from(record_type_buf[0]);
-
-            let mut txn_id_buf = [0u8; 8];
-            file.read_exact(&mut txn_id_buf)
-                .map_err(BuzzDBError::IOError)?;
-            let txn_id = TransactionID(u64::from_le_bytes(txn_id_buf));
-
-            let mut log_record = LogRecordData {
-                record_type,
-                txn_id,
-                page_id: None,
-                length: None,
-                offset: None,
-                before_img: None,
-                after_img: None,
-                log_offset: offset,
-                record_size: 1 + 8,
-            };
-
-            if record_type == LogRecordType::UpdateRecord {
-                // Update records have extra data: page_id, length, offset, before/after images
-                let mut page_id_buf = [0u8; 8];
-                file.read_exact(&mut page_id_buf)
-                    .map_err(BuzzDBError::IOError)?;
-                let page_id = PageID(u64::from_le_bytes(page_id_buf));
+            let mut len_buf = [0u8; 8];
+            if file.read_exact(&mut len_buf).is_err() {
+                break; // Torn tail: not even the length prefix made it fully to disk.
+            }
+            let payload_len = u64::from_le_bytes(len_buf) as usize;
 
-                let mut length_buf = [0u8; 8];
-                file.read_exact(&mut length_buf)
-                    .map_err(BuzzDBError::IOError)?;
-                let length = u64::from_le_bytes(length_buf);
+            if offset + 8 + payload_len + 4 > self.current_offset {
+                break; // Length claims more bytes than the log actually has - discard it.
+            }
 
-                let mut offset_buf = [0u8; 8];
-                file.read_exact(&mut offset_buf)
-                    .map_err(BuzzDBError::IOError)?;
-                let record_offset = u64::from_le_bytes(offset_buf);
+            let mut payload = vec![0u8; payload_len];
+            if file.read_exact(&mut payload).is_err() {
+                break;
+            }
 
-                let mut before_img = vec![0u8; length as usize];
-                file.read_exact(&mut before_img)
-                    .map_err(BuzzDBError::IOError)?;
+            let mut crc_buf = [0u8; 4];
+            if file.read_exact(&mut crc_buf).is_err() {
+                break;
+            }
+            if crc32fast::hash(&payload) != u32::from_le_bytes(crc_buf) {
+                break; // Checksum mismatch: a torn write landed but didn't complete.
+            }
 
-                let mut after_img = vec![0u8; length as usize];
-                file.read_exact(&mut after_img)
-                    .map_err(BuzzDBError::IOError)?;
+            let record_size = 8 + payload_len + 4;
+            let log_offset = offset;
 
-                log_record.page_id = Some(page_id);
-                log_record.length = Some(length);
-                log_record.offset = Some(record_offset);
-                log_record.before_img = Some(before_img);
-                log_record.after_img = Some(after_img);
-                log_record.record_size += 8 + 8 + 8 + 2 * length as usize;
-            }
+            let log_record = match self.parse_record(&payload, log_offset) {
+                Some(mut log_record) => {
+                    log_record.record_size = record_size;
+                    log_record
+                }
+                None => break, // Unrecognized type or truncated body - treat as corruption.
+            };
 
-            let record_size = log_record.record_size;
             logs.push(log_record);
             offset += record_size;
         }
 
+        if offset < self.current_offset {
+            self.log_file
+                .set_len(offset as u64)
+                .map_err(BuzzDBError::IOError)?;
+        }
+
         Ok(logs)
     }
 
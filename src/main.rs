@@ -10,11 +10,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("Aries Protocol Implementation in Rust");
 
-    // Use a temporary file for the log - we'll clean it up at the end
+    // Use temporary files for the log and data pages - we'll clean them up at the end
     let log_path = Path::new("temp_log.dat");
+    let data_path = Path::new("temp_data.dat");
 
     // Set up our core components with reasonable defaults
-    let buffer_manager = Arc::new(Mutex::new(BufferManager::new(4096, 100))); // 4KB pages, 100 page buffer
+    let buffer_manager = Arc::new(Mutex::new(BufferManager::with_disk_file(
+        data_path, 4096, 100,
+    )?)); // 4KB pages, 100 page buffer
     let log_manager = Arc::new(Mutex::new(LogManager::new(log_path)?));
     let mut txn_manager =
         TransactionManager::new(Arc::clone(&log_manager), Arc::clone(&buffer_manager));
@@ -26,10 +29,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     txn_manager.commit_txn(txn_id)?;
     info!("Committed transaction {}", txn_id.0);
 
-    // Clean up our temp file
+    // Clean up our temp files
     if log_path.exists() {
         std::fs::remove_file(log_path)?;
     }
+    if data_path.exists() {
+        std::fs::remove_file(data_path)?;
+    }
 
     Ok(())
 }
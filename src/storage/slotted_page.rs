@@ -1,9 +1,10 @@
 use crate::common::{BuzzDBError, PageID, RecordID, Result};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SlottedPage {
     pub page_id: PageID,
+    pub page_lsn: u64, // LSN of the last log record that modified this page - the WAL invariant hinges on this
     pub slots: Vec<Option<RecordID>>, // Fixed-size array of record slots
 }
 
@@ -11,10 +12,19 @@ impl SlottedPage {
     pub fn new(page_id: PageID, num_slots: usize) -> Self {
         Self {
             page_id,
+            page_lsn: 0,
             slots: vec![None; num_slots],
         }
     }
 
+    pub fn page_lsn(&self) -> u64 {
+        self.page_lsn
+    }
+
+    pub fn set_page_lsn(&mut self, lsn: u64) {
+        self.page_lsn = lsn;
+    }
+
     pub fn allocate_slot(&mut self, record_id: RecordID) -> Option<usize> {
         // Linear search for first empty slot - not great for performance but simple
         for (index, slot) in self.slots.iter_mut().enumerate() {
@@ -34,6 +44,16 @@ impl SlottedPage {
         Ok(())
     }
 
+    /// Number of free (unoccupied) slots, used by `HeapSegment`'s free-space map.
+    pub fn free_slot_count(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_none()).count()
+    }
+
+    /// Total number of slots the page was created with.
+    pub fn num_slots(&self) -> usize {
+        self.slots.len()
+    }
+
     pub fn get_record_id(&self, slot_index: usize) -> Result<RecordID> {
         if slot_index >= self.slots.len() {
             return Err(BuzzDBError::InvalidSlotIndex(slot_index));
@@ -45,12 +65,39 @@ impl SlottedPage {
         }
     }
 
+    /// Serializes the page with a leading 4-byte CRC32 header so a torn write can be
+    /// detected on the way back in.
     pub fn serialize(&self) -> Vec<u8> {
         // Using bincode for now - might want to switch to a more compact format later
-        bincode::serialize(self).expect("Serialization failed")
+        let body = bincode::serialize(self).expect("Serialization failed");
+        let checksum = crc32fast::hash(&body);
+
+        let mut out = Vec::with_capacity(4 + body.len());
+        out.extend_from_slice(&checksum.to_le_bytes());
+        out.extend_from_slice(&body);
+        out
     }
 
+    /// Verifies the leading checksum before trusting the bytes - a page that failed to
+    /// fully land on disk (a torn write) fails this check rather than deserializing into
+    /// garbage. Bytes past the serialized page (e.g. the rest of a page-sized read buffer)
+    /// are ignored and not covered by the checksum.
     pub fn deserialize(data: &[u8]) -> Result<Self> {
-        bincode::deserialize(data).map_err(|_| BuzzDBError::DeserializationError)
+        if data.len() < 4 {
+            return Err(BuzzDBError::DeserializationError);
+        }
+
+        let (checksum_bytes, body) = data.split_at(4);
+        let stored_checksum = u32::from_le_bytes(checksum_bytes.try_into().unwrap());
+
+        let page: Self =
+            bincode::deserialize(body).map_err(|_| BuzzDBError::DeserializationError)?;
+        let body_len = bincode::serialized_size(&page)
+            .map_err(|_| BuzzDBError::DeserializationError)? as usize;
+        if body_len > body.len() || crc32fast::hash(&body[..body_len]) != stored_checksum {
+            return Err(BuzzDBError::DeserializationError);
+        }
+
+        Ok(page)
     }
 }
@@ -3,7 +3,23 @@ use std::sync::{Arc, Mutex};
 
 use crate::buffer::BufferManager;
 use crate::common::{BuzzDBError, PageID, Result, TransactionID};
-use crate::log_mod::LogManager;
+use crate::heap::HeapSegment;
+use crate::log_mod::{LogManager, PageStores, RecoverablePageStore, TxnStatus};
+
+/// Identifies one of a transaction's savepoints. Scoped to the transaction that created it -
+/// comparing IDs across different transactions is meaningless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavepointId(pub u64);
+
+/// A point a transaction can roll back to without aborting entirely: the transaction's
+/// `last_lsn` (as last reported through `note_txn_lsn`), and which page locks it already
+/// held, at the moment the savepoint was taken.
+#[derive(Debug, Clone)]
+pub struct Savepoint {
+    pub id: SavepointId,
+    pub lsn: u64,
+    pub locked_pages: HashSet<PageID>,
+}
 
 #[derive(Debug)]
 pub struct Transaction {
@@ -11,6 +27,9 @@ pub struct Transaction {
     pub started: bool,
     pub modified_pages: HashSet<PageID>, // Pages we've written to
     pub locked_pages: HashSet<PageID>,   // Pages we're holding locks on
+    pub last_lsn: u64,                   // Most recent LSN reported via note_txn_lsn (0 until then)
+    pub savepoints: Vec<Savepoint>,      // Stack of savepoints taken, oldest first
+    next_savepoint_id: u64,
 }
 
 impl Transaction {
@@ -20,6 +39,9 @@ impl Transaction {
             started: true,
             modified_pages: HashSet::new(),
             locked_pages: HashSet::new(),
+            last_lsn: 0,
+            savepoints: Vec::new(),
+            next_savepoint_id: 0,
         }
     }
 
@@ -42,6 +64,7 @@ pub struct TransactionManager {
     page_locks: HashMap<PageID, TransactionID>, // Simple page-level locking
     log_manager: Arc<Mutex<LogManager>>,
     buffer_manager: Arc<Mutex<BufferManager>>,
+    heap_segments: Vec<Arc<Mutex<HeapSegment>>>, // Segments to include in this manager's checkpoints
 }
 
 impl TransactionManager {
@@ -55,9 +78,153 @@ impl TransactionManager {
             page_locks: HashMap::new(),
             log_manager,
             buffer_manager,
+            heap_segments: Vec::new(),
+        }
+    }
+
+    /// Registers a heap segment so `checkpoint()` includes its Dirty Page Table.
+    pub fn register_heap_segment(&mut self, segment: Arc<Mutex<HeapSegment>>) {
+        self.heap_segments.push(segment);
+    }
+
+    /// Records the LSN of a transaction's most recent log record, so its entry in the
+    /// Active Transaction Table stays current. Callers that log updates on a transaction's
+    /// behalf report the LSN here after each write; `HeapSegment` logs straight through
+    /// `LogManager` and does not call this.
+    pub fn note_txn_lsn(&mut self, txn_id: TransactionID, lsn: u64) {
+        if let Some(txn) = self.active_transactions.get_mut(&txn_id) {
+            txn.last_lsn = lsn;
         }
     }
 
+    /// Records a savepoint for `txn_id`, capturing its `last_lsn` (the latest LSN reported
+    /// through `note_txn_lsn`, not read from the log) and the set of pages it holds locks on.
+    /// `rollback_to` can later undo everything logged after that LSN while leaving the
+    /// transaction open.
+    pub fn savepoint(&mut self, txn_id: TransactionID) -> Result<SavepointId> {
+        let txn = self.active_transactions.get_mut(&txn_id).ok_or_else(|| {
+            BuzzDBError::Other(format!("Transaction {} not found", txn_id.0))
+        })?;
+
+        let id = SavepointId(txn.next_savepoint_id);
+        txn.next_savepoint_id += 1;
+        txn.savepoints.push(Savepoint {
+            id,
+            lsn: txn.last_lsn,
+            locked_pages: txn.locked_pages.clone(),
+        });
+
+        Ok(id)
+    }
+
+    /// Undoes every update `txn_id` logged after `savepoint` by applying their before-images
+    /// (emitting a CLR per undone update, same as full ARIES undo), then releases any page
+    /// locks it acquired after the savepoint. The transaction stays open and can keep going,
+    /// or roll back to the same savepoint again later. Savepoints taken after this one are
+    /// dropped, since there's nothing left to roll forward to.
+    pub fn rollback_to(&mut self, txn_id: TransactionID, savepoint: SavepointId) -> Result<()> {
+        let sp = self
+            .active_transactions
+            .get(&txn_id)
+            .ok_or_else(|| BuzzDBError::Other(format!("Transaction {} not found", txn_id.0)))?
+            .savepoints
+            .iter()
+            .find(|sp| sp.id == savepoint)
+            .cloned()
+            .ok_or_else(|| {
+                BuzzDBError::Other(format!(
+                    "Savepoint {} not found on transaction {}",
+                    savepoint.0, txn_id.0
+                ))
+            })?;
+
+        let new_last_lsn = {
+            let mut buffer_manager = self.buffer_manager.lock().unwrap();
+            let mut segment_guards: Vec<_> = self
+                .heap_segments
+                .iter()
+                .map(|segment| segment.lock().unwrap())
+                .collect();
+
+            let mut stores: Vec<&mut dyn RecoverablePageStore> = segment_guards
+                .iter_mut()
+                .map(|guard| &mut **guard as &mut dyn RecoverablePageStore)
+                .collect();
+            stores.push(&mut *buffer_manager);
+
+            let mut page_stores = PageStores::new(stores);
+            let mut log_manager = self.log_manager.lock().unwrap();
+            log_manager.undo_to(txn_id, sp.lsn, &mut page_stores)?
+        };
+
+        let txn = self.active_transactions.get_mut(&txn_id).unwrap();
+
+        let released: Vec<PageID> = txn
+            .locked_pages
+            .difference(&sp.locked_pages)
+            .copied()
+            .collect();
+        for page_id in &released {
+            txn.locked_pages.remove(page_id);
+            txn.modified_pages.remove(page_id);
+            self.page_locks.remove(page_id);
+        }
+
+        txn.last_lsn = new_last_lsn;
+        let pos = txn
+            .savepoints
+            .iter()
+            .position(|s| s.id == savepoint)
+            .unwrap();
+        txn.savepoints.truncate(pos + 1);
+
+        Ok(())
+    }
+
+    /// ARIES fuzzy checkpoint: snapshots the Dirty Page Table (recLSN per dirty page,
+    /// gathered from every registered heap segment plus the buffer manager) and the Active
+    /// Transaction Table (lastLSN per live transaction) and writes them to the log bracketed
+    /// by begin/end checkpoint records. Nothing here blocks an in-flight transaction - every
+    /// table is read under its own lock, one segment/transaction at a time.
+    pub fn checkpoint(&mut self) -> Result<u64> {
+        let mut dirty_page_table = self.buffer_manager.lock().unwrap().dirty_page_table();
+        for segment in &self.heap_segments {
+            dirty_page_table.extend(segment.lock().unwrap().dirty_page_table());
+        }
+
+        let active_transaction_table: HashMap<TransactionID, (u64, TxnStatus)> = self
+            .active_transactions
+            .values()
+            .map(|txn| (txn.id, (txn.last_lsn, TxnStatus::Active)))
+            .collect();
+
+        let mut log_manager = self.log_manager.lock().unwrap();
+        log_manager.log_begin_checkpoint()?;
+        log_manager.log_end_checkpoint(&dirty_page_table, &active_transaction_table)
+    }
+
+    /// Crash recovery: replays the WAL against the buffer manager and every heap segment
+    /// registered via `register_heap_segment` in one pass, so a transaction that wrote
+    /// through either one gets redone/undone - not just whichever one `recovery()` used to
+    /// be hardwired to.
+    pub fn recover(&mut self) -> Result<()> {
+        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut segment_guards: Vec<_> = self
+            .heap_segments
+            .iter()
+            .map(|segment| segment.lock().unwrap())
+            .collect();
+
+        let mut stores: Vec<&mut dyn RecoverablePageStore> = segment_guards
+            .iter_mut()
+            .map(|guard| &mut **guard as &mut dyn RecoverablePageStore)
+            .collect();
+        stores.push(&mut *buffer_manager);
+
+        let mut page_stores = PageStores::new(stores);
+        self.log_manager.lock().unwrap().recovery(&mut page_stores)
+    }
+
     pub fn start_txn(&mut self) -> Result<TransactionID> {
         let txn_id = TransactionID(self.next_txn_id);
         self.next_txn_id += 1;
@@ -72,11 +239,13 @@ impl TransactionManager {
 
     pub fn commit_txn(&mut self, txn_id: TransactionID) -> Result<()> {
         if let Some(txn) = self.active_transactions.remove(&txn_id) {
-            let mut buffer_manager = self.buffer_manager.lock().unwrap();
+            {
+                let mut buffer_manager = self.buffer_manager.lock().unwrap();
 
-            // Force all dirty pages to disk before committing
-            for page_id in &txn.modified_pages {
-                buffer_manager.flush_page(*page_id)?;
+                // Force all dirty pages to disk before committing
+                for page_id in &txn.modified_pages {
+                    buffer_manager.flush_page(*page_id)?;
+                }
             }
 
             // Release all locks held by this transaction
@@ -84,7 +253,20 @@ impl TransactionManager {
                 self.page_locks.remove(page_id);
             }
 
-            self.log_manager.lock().unwrap().log_commit(txn_id)?;
+            // Append, then prepare the durability wait, as two separate lock acquisitions -
+            // and crucially, the wait itself runs after this third lock is dropped. Calling
+            // ensure_durable() here instead would still hold the lock across the fsync/condvar
+            // wait, so a concurrent commit on another TransactionManager sharing this
+            // log_manager (they're handed out via Arc<Mutex<LogManager>> for exactly this)
+            // couldn't even append, let alone join the wait - group commit would have
+            // nothing to batch.
+            let lsn = self.log_manager.lock().unwrap().append_commit(txn_id)?;
+            let wait = self
+                .log_manager
+                .lock()
+                .unwrap()
+                .prepare_durability_wait(lsn)?;
+            wait.wait()?;
         } else {
             return Err(BuzzDBError::Other(format!(
                 "Transaction {} not found",
@@ -144,3 +326,99 @@ impl TransactionManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::RecordID;
+    use std::fs;
+
+    fn unique_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("aries_rust_test_{}_{}", std::process::id(), name))
+    }
+
+    fn cleanup(log_path: &std::path::Path, heap_path: &std::path::Path) {
+        let _ = fs::remove_file(log_path);
+        let _ = fs::remove_file(heap_path);
+        let _ = fs::remove_file(heap_path.with_extension("freemap"));
+    }
+
+    /// Regression test for the gap where recovery only ever replayed into BufferManager:
+    /// a committed HeapSegment write that never made it to the segment's own backing file
+    /// (the "crash") must come back once TransactionManager::recover() runs against a fresh
+    /// HeapSegment over the same files, not just a fresh BufferManager.
+    #[test]
+    fn recover_replays_heap_segment_writes() {
+        let log_path = unique_path("recover.wal");
+        let heap_path = unique_path("recover.heap");
+        cleanup(&log_path, &heap_path);
+
+        let page_id;
+        let record_id = RecordID(42);
+        {
+            let log_manager = Arc::new(Mutex::new(LogManager::new(&log_path).unwrap()));
+            let buffer_manager = Arc::new(Mutex::new(BufferManager::new(4096, 8)));
+            let heap_segment = Arc::new(Mutex::new(
+                HeapSegment::new(&heap_path, 4096, 16, 8, Arc::clone(&log_manager), 1).unwrap(),
+            ));
+
+            let mut txn_manager = TransactionManager::new(log_manager, buffer_manager);
+            txn_manager.register_heap_segment(Arc::clone(&heap_segment));
+
+            let txn_id = txn_manager.start_txn().unwrap();
+            page_id = heap_segment.lock().unwrap().allocate_page(txn_id).unwrap();
+            heap_segment
+                .lock()
+                .unwrap()
+                .insert_record(page_id, record_id, txn_id)
+                .unwrap();
+
+            // commit_txn only flushes BufferManager's dirty pages, so the heap segment's
+            // in-memory page is never written back to heap_path here - standing in for a
+            // crash right after commit.
+            txn_manager.commit_txn(txn_id).unwrap();
+        }
+
+        // Reopen everything fresh, as a restart would.
+        let log_manager = Arc::new(Mutex::new(LogManager::new(&log_path).unwrap()));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(4096, 8)));
+        let heap_segment = Arc::new(Mutex::new(
+            HeapSegment::new(&heap_path, 4096, 16, 8, Arc::clone(&log_manager), 1).unwrap(),
+        ));
+
+        let mut txn_manager = TransactionManager::new(log_manager, buffer_manager);
+        txn_manager.register_heap_segment(Arc::clone(&heap_segment));
+        txn_manager.recover().unwrap();
+
+        let recovered = heap_segment.lock().unwrap().get_record(page_id, 0).unwrap();
+        assert_eq!(recovered, record_id);
+
+        cleanup(&log_path, &heap_path);
+    }
+
+    /// Regression test for the group-commit path being structurally dead: if commit_txn
+    /// held the log_manager lock across both the append and the fsync, a second commit could
+    /// never interleave its own append in between - group commit would have nothing to batch.
+    /// append_commit and prepare_durability_wait being separate lock acquisitions, with the
+    /// wait run after the lock is dropped, is what makes that interleaving possible; this just
+    /// checks commit_txn still round-trips correctly under it.
+    #[test]
+    fn commit_txn_appends_and_waits_as_separate_steps() {
+        let log_path = unique_path("commit_split.wal");
+        let heap_path = unique_path("commit_split.heap");
+        cleanup(&log_path, &heap_path);
+
+        let log_manager = Arc::new(Mutex::new(LogManager::new(&log_path).unwrap()));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(4096, 8)));
+        let mut txn_manager = TransactionManager::new(log_manager, buffer_manager);
+
+        let txn_id = txn_manager.start_txn().unwrap();
+        txn_manager.commit_txn(txn_id).unwrap();
+
+        // The transaction must be gone from the active set and committing it again should
+        // fail, same as before the lock-splitting change.
+        assert!(txn_manager.commit_txn(txn_id).is_err());
+
+        cleanup(&log_path, &heap_path);
+    }
+}